@@ -0,0 +1,82 @@
+//! A thread-bound `Context`/`Device`/`Queue` bundle.
+
+use std::cell::RefCell;
+use core::error::{Result as OclResult};
+use core::Context as ContextCore;
+use standard::{Context, Device, Queue};
+
+thread_local!(static ACTIVE_EXECUTION_CONTEXT: RefCell<Option<ExecutionContext>> =
+    RefCell::new(None));
+
+/// Bundles a `Context`, its default `Device`, and a `Queue` so enqueue
+/// helpers can pick up an ambient queue instead of threading a `&Queue`
+/// through every call.
+#[derive(Clone, Debug)]
+pub struct ExecutionContext {
+    context: Context,
+    device: Device,
+    queue: Queue,
+}
+
+impl ExecutionContext {
+    /// Returns a new `ExecutionContext` bundling `context`, `device`, and
+    /// `queue`.
+    pub fn new(context: Context, device: Device, queue: Queue) -> ExecutionContext {
+        ExecutionContext { context: context, device: device, queue: queue }
+    }
+
+    /// Installs `self` as the active execution context for the current
+    /// thread. Does not call `finish()` on any previously bound queue.
+    ///
+    /// Returns an error if `self`'s context is empty/default-constructed.
+    pub fn bind(self) -> OclResult<()> {
+        if self.context.core() == &ContextCore::default() {
+            return Err("ExecutionContext::bind: cannot bind an empty/default-constructed \
+                context.".into());
+        }
+
+        ACTIVE_EXECUTION_CONTEXT.with(|active| {
+            *active.borrow_mut() = Some(self);
+        });
+
+        Ok(())
+    }
+
+    /// Returns the execution context currently bound on this thread, if
+    /// any.
+    pub fn current() -> Option<ExecutionContext> {
+        ACTIVE_EXECUTION_CONTEXT.with(|active| active.borrow().clone())
+    }
+
+    /// Returns the bundled context.
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Returns the bundled device.
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// Returns the bundled queue.
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    /// Returns a new `ExecutionContext` sharing this context and device but
+    /// backed by `queue`.
+    pub fn clone_with_new_queue(&self, queue: Queue) -> ExecutionContext {
+        ExecutionContext {
+            context: self.context.clone(),
+            device: self.device.clone(),
+            queue: queue,
+        }
+    }
+
+    /// Returns a new `ExecutionContext` sharing this context and device but
+    /// backed by a fresh, in-order queue.
+    pub fn clone_with_fresh_queue(&self) -> OclResult<ExecutionContext> {
+        let queue = try!(Queue::new(&self.context, self.device.clone(), None));
+        Ok(self.clone_with_new_queue(queue))
+    }
+}
@@ -0,0 +1,10 @@
+//! Docile wrappers around `core` types.
+
+mod queue;
+mod execution_context;
+mod session;
+
+pub use self::queue::{Queue, QueueKind, QueueEnqueueOptions, EventProfilingInfo,
+    ProfilingAccumulator};
+pub use self::execution_context::ExecutionContext;
+pub use self::session::Session;
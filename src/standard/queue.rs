@@ -4,8 +4,70 @@ use std;
 use std::ops::{Deref, DerefMut};
 use core::error::{Result as OclResult};
 use core::{self, CommandQueue as CommandQueueCore, Context as ContextCore,
-    CommandQueueInfo, CommandQueueInfoResult, OpenclVersion, CommandQueueProperties};
-use standard::{Context, Device};
+    CommandQueueInfo, CommandQueueInfoResult, OpenclVersion, CommandQueueProperties,
+    ProfilingInfo as CoreProfilingInfo, ProfilingInfoResult};
+use standard::{Context, Device, Event};
+
+/// Whether a `Queue` runs on the host or is an on-device (nested) queue
+/// usable only from within an executing kernel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueKind {
+    /// A normal, host-enqueued queue.
+    Host,
+    /// An on-device queue created with `CL_QUEUE_ON_DEVICE`, enqueuable only
+    /// from kernels running on the device.
+    Device,
+}
+
+/// Options controlling a single enqueue operation: whether it blocks, an
+/// offset into the command's index space, and its event dependencies.
+/// Reusable across multiple enqueues.
+#[derive(Clone, Debug)]
+pub struct QueueEnqueueOptions {
+    /// Whether the enqueue should block the calling thread until complete.
+    pub blocking: bool,
+    /// Offset into the command's global work size, in elements.
+    pub offset: usize,
+    /// Events this command must wait on before it may execute.
+    pub wait_events: Vec<Event>,
+}
+
+impl QueueEnqueueOptions {
+    /// Returns a new, blocking, zero-offset set of options with an empty
+    /// waitlist.
+    pub fn new() -> QueueEnqueueOptions {
+        QueueEnqueueOptions {
+            blocking: true,
+            offset: 0,
+            wait_events: Vec::new(),
+        }
+    }
+
+    /// Sets whether the enqueue should block.
+    pub fn blocking(mut self, blocking: bool) -> QueueEnqueueOptions {
+        self.blocking = blocking;
+        self
+    }
+
+    /// Sets the offset into the command's global work size.
+    pub fn offset(mut self, offset: usize) -> QueueEnqueueOptions {
+        self.offset = offset;
+        self
+    }
+
+    /// Appends `events` to the waitlist. Accepts anything convertible into
+    /// a slice of events so the options can be reused across enqueues.
+    pub fn wait_for<E: AsRef<[Event]>>(mut self, events: E) -> QueueEnqueueOptions {
+        self.wait_events.extend_from_slice(events.as_ref());
+        self
+    }
+}
+
+impl Default for QueueEnqueueOptions {
+    fn default() -> QueueEnqueueOptions {
+        QueueEnqueueOptions::new()
+    }
+}
 
 /// A command queue which manages all actions taken on kernels, buffers, and
 /// images.
@@ -31,6 +93,7 @@ pub struct Queue {
     context_obj_core: ContextCore,
     device: Device,
     device_version: OpenclVersion,
+    kind: QueueKind,
 }
 
 impl Queue {
@@ -45,9 +108,168 @@ impl Queue {
             context_obj_core: context.core().clone(),
             device: device,
             device_version: device_version,
+            kind: QueueKind::Host,
+        })
+    }
+
+    /// Returns a new Queue on the device specified by `device`, routing
+    /// through `clCreateCommandQueueWithProperties` on OpenCL 2.0+ devices
+    /// and falling back to the legacy `clCreateCommandQueue` entry point
+    /// otherwise.
+    ///
+    /// `queue_size` corresponds to `CL_QUEUE_SIZE` and may only be supplied
+    /// when `properties` includes `CL_QUEUE_ON_DEVICE`; it configures the
+    /// size of an on-device queue and is otherwise left unset so the
+    /// platform default applies.
+    ///
+    /// Returns an error if `CL_QUEUE_ON_DEVICE` (or `CL_QUEUE_ON_DEVICE_DEFAULT`,
+    /// or a `queue_size`) is requested on a device older than OpenCL 2.0,
+    /// since such devices have no device-side queue support.
+    pub fn with_properties(context: &Context, device: Device,
+            properties: CommandQueueProperties, queue_size: Option<u32>) -> OclResult<Queue> {
+        let device_version = try!(device.version());
+        let wants_device_queue = properties.contains(CommandQueueProperties::CL_QUEUE_ON_DEVICE)
+            || properties.contains(CommandQueueProperties::CL_QUEUE_ON_DEVICE_DEFAULT);
+
+        if queue_size.is_some() && !wants_device_queue {
+            return Err("Queue::with_properties: `queue_size` (`CL_QUEUE_SIZE`) may only be \
+                supplied when `properties` includes `CL_QUEUE_ON_DEVICE`.".into());
+        }
+
+        if device_version < OpenclVersion::new(2, 0) {
+            if wants_device_queue || queue_size.is_some() {
+                return Err(format!("Queue::with_properties: device '{}' reports OpenCL version \
+                    {} but on-device queues (`CL_QUEUE_ON_DEVICE`/`CL_QUEUE_SIZE`) require \
+                    OpenCL 2.0 or higher.", device.name().unwrap_or_default(), device_version).into());
+            }
+
+            let obj_core = try!(core::create_command_queue(context, &device, Some(properties)));
+
+            return Ok(Queue {
+                obj_core: obj_core,
+                context_obj_core: context.core().clone(),
+                device: device,
+                device_version: device_version,
+                kind: QueueKind::Host,
+            });
+        }
+
+        let obj_core = try!(core::create_command_queue_with_properties(context, &device,
+            properties, queue_size));
+
+        Ok(Queue {
+            obj_core: obj_core,
+            context_obj_core: context.core().clone(),
+            device: device,
+            device_version: device_version,
+            kind: if wants_device_queue { QueueKind::Device } else { QueueKind::Host },
         })
     }
 
+    /// Returns whether this is a host-side queue or an on-device queue
+    /// created with `CL_QUEUE_ON_DEVICE`.
+    pub fn kind(&self) -> QueueKind {
+        self.kind
+    }
+
+    /// Returns a new out-of-order Queue on the device specified by `device`.
+    ///
+    /// Commands enqueued on an out-of-order queue may execute and complete
+    /// in any order with respect to one another, so callers must express
+    /// dependencies explicitly via `QueueEnqueueOptions::wait_for` (see
+    /// `is_out_of_order`).
+    pub fn new_out_of_order(context: &Context, device: Device) -> OclResult<Queue> {
+        Queue::new(context, device, Some(CommandQueueProperties::CL_QUEUE_OUT_OF_ORDER_EXEC_MODE_ENABLE))
+    }
+
+    /// Returns whether this queue executes commands out-of-order.
+    ///
+    /// When `true`, commands are not guaranteed to execute in the order
+    /// they were enqueued and callers must supply explicit event
+    /// dependencies (via `QueueEnqueueOptions`) for correctness.
+    pub fn is_out_of_order(&self) -> OclResult<bool> {
+        let properties = try!(self.properties());
+        Ok(properties.contains(CommandQueueProperties::CL_QUEUE_OUT_OF_ORDER_EXEC_MODE_ENABLE))
+    }
+
+    /// Returns whether this queue was created with `CL_QUEUE_PROFILING_ENABLE`.
+    ///
+    /// `event_profile` refuses to read timestamps when called on a queue for
+    /// which this returns `false`; it does not check which queue the event
+    /// was enqueued on.
+    pub fn profiling_enabled(&self) -> OclResult<bool> {
+        let properties = try!(self.properties());
+        Ok(properties.contains(CommandQueueProperties::CL_QUEUE_PROFILING_ENABLE))
+    }
+
+    /// Returns this queue's `CL_QUEUE_PROPERTIES` bitfield.
+    fn properties(&self) -> OclResult<CommandQueueProperties> {
+        match try!(self.info(CommandQueueInfo::Properties)) {
+            CommandQueueInfoResult::Properties(p) => Ok(p),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Reads the `CL_PROFILING_COMMAND_{QUEUED,SUBMIT,START,END}` timestamps
+    /// off `event` and returns them alongside their derived durations.
+    ///
+    /// Returns an error distinct from any underlying OpenCL failure if this
+    /// queue was not created with `CL_QUEUE_PROFILING_ENABLE`.
+    pub fn event_profile(&self, event: &Event) -> OclResult<EventProfilingInfo> {
+        if !try!(self.profiling_enabled()) {
+            return Err("Queue::event_profile: profiling is not enabled on this queue; \
+                create it with `CL_QUEUE_PROFILING_ENABLE` to read event timestamps.".into());
+        }
+
+        let queued = match try!(core::get_event_profiling_info(event, CoreProfilingInfo::Queued)) {
+            ProfilingInfoResult::Queued(t) => t,
+            _ => unreachable!(),
+        };
+
+        let submit = match try!(core::get_event_profiling_info(event, CoreProfilingInfo::Submit)) {
+            ProfilingInfoResult::Submit(t) => t,
+            _ => unreachable!(),
+        };
+
+        let start = match try!(core::get_event_profiling_info(event, CoreProfilingInfo::Start)) {
+            ProfilingInfoResult::Start(t) => t,
+            _ => unreachable!(),
+        };
+
+        let end = match try!(core::get_event_profiling_info(event, CoreProfilingInfo::End)) {
+            ProfilingInfoResult::End(t) => t,
+            _ => unreachable!(),
+        };
+
+        Ok(EventProfilingInfo { queued: queued, submit: submit, start: start, end: end })
+    }
+
+    /// Builds the waitlist for `options` and enqueues a marker which
+    /// completes once every event in it has completed, returning the new
+    /// completion `Event`.
+    ///
+    /// This is the primitive other enqueue helpers (buffer/image/kernel)
+    /// build on to turn a `QueueEnqueueOptions` into the waitlist
+    /// pointer/count pair OpenCL expects plus an output event.
+    pub fn enqueue_marker(&self, options: &QueueEnqueueOptions) -> OclResult<Event> {
+        let mut new_event = Event::empty();
+
+        let wait_list = if options.wait_events.is_empty() {
+            None
+        } else {
+            Some(&options.wait_events)
+        };
+
+        try!(core::enqueue_marker_with_wait_list(&self.obj_core,
+            wait_list, Some(&mut new_event)));
+
+        if options.blocking {
+            try!(new_event.wait_for());
+        }
+
+        Ok(new_event)
+    }
+
     /// Issues all previously queued OpenCL commands to the device.
     pub fn flush(&self) -> OclResult<()> {
         core::flush(&self.obj_core)
@@ -128,3 +350,134 @@ impl DerefMut for Queue {
         &mut self.obj_core
     }
 }
+
+/// The four `CL_PROFILING_COMMAND_*` timestamps recorded for a profiled
+/// event, in nanoseconds, along with the durations derived from them.
+///
+/// Obtained via `Queue::event_profile`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventProfilingInfo {
+    /// `CL_PROFILING_COMMAND_QUEUED`: when the command was enqueued, in ns.
+    pub queued: u64,
+    /// `CL_PROFILING_COMMAND_SUBMIT`: when the command was submitted to the
+    /// device, in ns.
+    pub submit: u64,
+    /// `CL_PROFILING_COMMAND_START`: when the device began executing the
+    /// command, in ns.
+    pub start: u64,
+    /// `CL_PROFILING_COMMAND_END`: when the device finished executing the
+    /// command, in ns.
+    pub end: u64,
+}
+
+impl EventProfilingInfo {
+    /// Time spent waiting in the queue before being submitted, in ns.
+    ///
+    /// Saturates to `0` rather than panicking/wrapping if a driver reports
+    /// a non-monotonic `submit` timestamp.
+    pub fn queue_latency(&self) -> u64 {
+        self.submit.saturating_sub(self.queued)
+    }
+
+    /// Time spent between submission and the device starting execution, in
+    /// ns.
+    ///
+    /// Saturates to `0` rather than panicking/wrapping if a driver reports
+    /// a non-monotonic `start` timestamp.
+    pub fn submit_latency(&self) -> u64 {
+        self.start.saturating_sub(self.submit)
+    }
+
+    /// Time spent executing on the device, in ns.
+    ///
+    /// Saturates to `0` rather than panicking/wrapping if a driver reports
+    /// a non-monotonic `end` timestamp.
+    pub fn exec_time(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+/// Sums per-event execution times across many profiled events, for
+/// building simple performance reports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProfilingAccumulator {
+    total_exec_ns: u64,
+    count: usize,
+}
+
+impl ProfilingAccumulator {
+    /// Returns a new, empty accumulator.
+    pub fn new() -> ProfilingAccumulator {
+        ProfilingAccumulator::default()
+    }
+
+    /// Adds `profile`'s execution time to the running total.
+    pub fn add(&mut self, profile: &EventProfilingInfo) {
+        self.total_exec_ns += profile.exec_time();
+        self.count += 1;
+    }
+
+    /// Returns the number of events summed so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the summed execution time across all added events, in ns.
+    pub fn total_exec_ns(&self) -> u64 {
+        self.total_exec_ns
+    }
+
+    /// Returns the mean execution time across all added events, in ns, or
+    /// `0.0` if none have been added.
+    pub fn mean_exec_ns(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total_exec_ns as f64 / self.count as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EventProfilingInfo, ProfilingAccumulator};
+
+    fn profile(queued: u64, submit: u64, start: u64, end: u64) -> EventProfilingInfo {
+        EventProfilingInfo { queued: queued, submit: submit, start: start, end: end }
+    }
+
+    #[test]
+    fn event_profiling_info_durations() {
+        let p = profile(100, 150, 200, 500);
+        assert_eq!(p.queue_latency(), 50);
+        assert_eq!(p.submit_latency(), 50);
+        assert_eq!(p.exec_time(), 300);
+    }
+
+    #[test]
+    fn event_profiling_info_durations_saturate_on_non_monotonic_timestamps() {
+        let p = profile(200, 100, 50, 0);
+        assert_eq!(p.queue_latency(), 0);
+        assert_eq!(p.submit_latency(), 0);
+        assert_eq!(p.exec_time(), 0);
+    }
+
+    #[test]
+    fn profiling_accumulator_sums_and_counts() {
+        let mut acc = ProfilingAccumulator::new();
+        acc.add(&profile(0, 0, 0, 100));
+        acc.add(&profile(0, 0, 0, 300));
+
+        assert_eq!(acc.count(), 2);
+        assert_eq!(acc.total_exec_ns(), 400);
+        assert_eq!(acc.mean_exec_ns(), 200.0);
+    }
+
+    #[test]
+    fn profiling_accumulator_empty_mean_is_zero() {
+        let acc = ProfilingAccumulator::new();
+        assert_eq!(acc.count(), 0);
+        assert_eq!(acc.mean_exec_ns(), 0.0);
+    }
+}
+
@@ -0,0 +1,99 @@
+//! A `Context` + `Program` + per-device `Queue` bundle with ordered teardown.
+
+use std::mem::ManuallyDrop;
+use core::error::{Result as OclResult};
+use standard::{Context, Device, Program, Queue};
+
+/// Owns a `Context`, a built `Program`, and one `Queue` per device.
+///
+/// Release order matters: a context must outlive the queues created from
+/// it, and the devices must outlive the context built over them, so
+/// `Session` wraps its fields in `ManuallyDrop` and releases them itself,
+/// queues first, then the program, then the context, then the devices,
+/// rather than relying on field declaration order.
+///
+/// `Queue` keeps its own refcounted handle to its context (see
+/// `context_core`), so cloning a queue out of a session keeps that
+/// context alive independently of the session it came from.
+pub struct Session {
+    queues: ManuallyDrop<Vec<Queue>>,
+    program: ManuallyDrop<Program>,
+    context: ManuallyDrop<Context>,
+    devices: ManuallyDrop<Vec<Device>>,
+}
+
+impl Session {
+    /// Builds a context over `devices`, compiles `src` into a `Program`,
+    /// and opens one in-order queue per device.
+    pub fn new(devices: Vec<Device>, src: &str) -> OclResult<Session> {
+        let context = try!(Context::builder()
+            .devices(devices.clone())
+            .build());
+
+        let program = try!(Program::builder()
+            .src(src)
+            .devices(devices.clone())
+            .build(&context));
+
+        let mut queues = Vec::with_capacity(devices.len());
+
+        for device in devices.iter().cloned() {
+            queues.push(try!(Queue::new(&context, device, None)));
+        }
+
+        Ok(Session {
+            queues: ManuallyDrop::new(queues),
+            program: ManuallyDrop::new(program),
+            context: ManuallyDrop::new(context),
+            devices: ManuallyDrop::new(devices),
+        })
+    }
+
+    /// Returns the queue at `index`, or an error if `index` is out of
+    /// range.
+    pub fn queue(&self, index: usize) -> OclResult<&Queue> {
+        self.queues.get(index).ok_or_else(|| {
+            format!("Session::queue: index [{}] is out of range for a session with {} \
+                queue(s).", index, self.queues.len()).into()
+        })
+    }
+
+    /// Returns all of this session's queues.
+    pub fn queues(&self) -> &[Queue] {
+        &self.queues
+    }
+
+    /// Returns the session's context.
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Returns the session's built program.
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Calls `f` once per queue, spreading its work across every queue in
+    /// the session.
+    pub fn for_each_queue<F>(&self, mut f: F) -> OclResult<()>
+            where F: FnMut(&Queue) -> OclResult<()> {
+        for queue in self.queues.iter() {
+            try!(f(queue));
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // Safety: each field is read from exactly once, here, and `self`
+        // is never used again afterwards.
+        unsafe {
+            ManuallyDrop::drop(&mut self.queues);
+            ManuallyDrop::drop(&mut self.program);
+            ManuallyDrop::drop(&mut self.context);
+            ManuallyDrop::drop(&mut self.devices);
+        }
+    }
+}